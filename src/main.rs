@@ -1,20 +1,69 @@
+use std::collections::HashMap;
 use std::net::IpAddr;
 use std::time::Duration;
 
 use anyhow::{anyhow, Context, Result};
-use huelib::resource::light::{AttributeModifier, Scanner, StateModifier};
+use huelib::resource::group::StateModifier as GroupStateModifier;
+use huelib::resource::light::{AttributeModifier, ModifierType, Scanner, StateModifier};
+use huelib::resource::scene::Scene;
 use huelib::Bridge;
 use prettytable::{cell, format, row, Table};
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 use structopt::StructOpt;
 
 const SCAN_SLEEP_TIME: Duration = Duration::from_secs(40);
 
 #[derive(Debug, Deserialize)]
 struct Config {
+    default: String,
+    profiles: HashMap<String, Profile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Profile {
+    ip_address: Option<IpAddr>,
     username: String,
 }
 
+/// Accepts both the current `{ default, profiles }` shape and the legacy
+/// single-bridge `{ username }` shape so existing configs keep working
+/// without a manual edit.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ConfigFile {
+    Profiles(Config),
+    Legacy { username: String },
+}
+
+impl ConfigFile {
+    fn into_config(self) -> Config {
+        match self {
+            ConfigFile::Profiles(config) => config,
+            ConfigFile::Legacy { username } => {
+                eprintln!(
+                    "warning: using legacy single-profile config.toml; treating it as a \
+                     \"default\" profile. Add [profiles.<name>] sections and a top-level \
+                     \"default\" key to silence this warning."
+                );
+
+                let mut profiles = HashMap::new();
+                profiles.insert(
+                    "default".to_string(),
+                    Profile {
+                        ip_address: None,
+                        username,
+                    },
+                );
+
+                Config {
+                    default: "default".to_string(),
+                    profiles,
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug, StructOpt)]
 enum Subcommand {
     /// Register a new username on a Hue bridge
@@ -32,6 +81,144 @@ enum Subcommand {
         id: String,
         name: String,
     },
+
+    Set {
+        id: String,
+
+        /// Brightness (0-254). Prefix with + or - to increment/decrement
+        /// instead of overriding.
+        #[structopt(long, parse(try_from_str = parse_modifier_value))]
+        brightness: Option<(ModifierType, i16)>,
+
+        /// Hue (0-65535). Prefix with + or - to increment/decrement instead
+        /// of overriding.
+        #[structopt(long, parse(try_from_str = parse_hue_modifier_value))]
+        hue: Option<(ModifierType, i32)>,
+
+        /// Saturation (0-254). Prefix with + or - to increment/decrement
+        /// instead of overriding.
+        #[structopt(long, parse(try_from_str = parse_modifier_value))]
+        saturation: Option<(ModifierType, i16)>,
+
+        /// Color temperature in mireds. Prefix with + or - to
+        /// increment/decrement instead of overriding.
+        #[structopt(long = "color-temp", parse(try_from_str = parse_modifier_value))]
+        color_temp: Option<(ModifierType, i16)>,
+
+        /// CIE xy color space coordinates, as "x,y"
+        #[structopt(long, parse(try_from_str = parse_xy))]
+        xy: Option<(f32, f32)>,
+
+        /// Transition time in milliseconds
+        #[structopt(long)]
+        transition: Option<u64>,
+    },
+
+    Group(GroupCommand),
+
+    Scene(SceneCommand),
+
+    /// Continuously redraw the light list, highlighting rows that changed
+    /// since the previous poll
+    Watch {
+        /// Poll interval, in seconds (minimum 1)
+        #[structopt(long, default_value = "2")]
+        interval: u64,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum GroupCommand {
+    /// List all groups
+    List,
+
+    /// Turn every light in a group on
+    On {
+        id: String,
+    },
+
+    /// Turn every light in a group off
+    Off {
+        id: String,
+    },
+
+    /// Set the on/off state of a group
+    State {
+        id: String,
+
+        #[structopt(parse(try_from_str = parse_on_off))]
+        on: bool,
+    },
+}
+
+fn parse_on_off(s: &str) -> Result<bool, String> {
+    match s {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(format!("expected \"on\" or \"off\", got \"{}\"", s)),
+    }
+}
+
+/// Parses a `StateModifier` value, treating a leading `+` or `-` as an
+/// increment relative to the current value and a bare number as an
+/// absolute override.
+fn parse_modifier_value(s: &str) -> Result<(ModifierType, i16), String> {
+    let modifier_type = if s.starts_with('+') || s.starts_with('-') {
+        ModifierType::Increment
+    } else {
+        ModifierType::Override
+    };
+
+    let value = s
+        .parse()
+        .map_err(|_| format!("\"{}\" is not a valid number", s))?;
+
+    Ok((modifier_type, value))
+}
+
+/// Like `parse_modifier_value`, but for hue, whose 0-65535 override range
+/// doesn't fit in `i16`.
+fn parse_hue_modifier_value(s: &str) -> Result<(ModifierType, i32), String> {
+    let modifier_type = if s.starts_with('+') || s.starts_with('-') {
+        ModifierType::Increment
+    } else {
+        ModifierType::Override
+    };
+
+    let value = s
+        .parse()
+        .map_err(|_| format!("\"{}\" is not a valid number", s))?;
+
+    Ok((modifier_type, value))
+}
+
+fn parse_xy(s: &str) -> Result<(f32, f32), String> {
+    let mut parts = s.splitn(2, ',');
+
+    let x = parts
+        .next()
+        .ok_or_else(|| format!("\"{}\" is missing an x coordinate", s))?
+        .parse()
+        .map_err(|_| format!("\"{}\" is not a valid x coordinate", s))?;
+
+    let y = parts
+        .next()
+        .ok_or_else(|| format!("\"{}\" is missing a y coordinate", s))?
+        .parse()
+        .map_err(|_| format!("\"{}\" is not a valid y coordinate", s))?;
+
+    Ok((x, y))
+}
+
+#[derive(Debug, StructOpt)]
+enum SceneCommand {
+    /// List all scenes
+    List,
+
+    /// Activate a scene on its group
+    Activate {
+        id: String,
+    },
 }
 
 #[derive(Debug, StructOpt)]
@@ -43,6 +230,72 @@ struct Args {
     /// Optional IP address for a specific bridge. Tool will search the network if no IP is
     /// provided.
     ip_address: Option<IpAddr>,
+
+    /// Print listings as JSON instead of a table
+    #[structopt(long, global = true)]
+    json: bool,
+
+    /// Named bridge profile to use, from config.toml. Defaults to the
+    /// profile named by the `default` key in the config file.
+    #[structopt(long, global = true)]
+    profile: Option<String>,
+}
+
+/// Matches `id` case-insensitively against `name` in `candidates`, erroring
+/// with a disambiguation list if more than one candidate matches.
+fn resolve_id(id: String, candidates: Vec<(String, String)>) -> Result<String> {
+    let mut matches: Vec<(String, String)> = candidates
+        .into_iter()
+        .filter(|(_, name)| name.eq_ignore_ascii_case(&id))
+        .collect();
+
+    match matches.len() {
+        0 => Err(anyhow!("no light or group named \"{}\" found", id)),
+        1 => Ok(matches.remove(0).0),
+        _ => Err(anyhow!(
+            "multiple matches for \"{}\": {}",
+            id,
+            matches
+                .iter()
+                .map(|(id, name)| format!("{} ({})", name, id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Resolves a light argument to its numeric id. If `id` already parses as a
+/// number it is returned unchanged without touching the bridge; otherwise
+/// all lights are fetched and matched by name.
+fn resolve_light_id(bridge: &Bridge, id: String) -> Result<String> {
+    if id.parse::<u32>().is_ok() {
+        return Ok(id);
+    }
+
+    let candidates = bridge
+        .get_all_lights()?
+        .into_iter()
+        .map(|light| (light.id, light.name))
+        .collect();
+
+    resolve_id(id, candidates)
+}
+
+/// Resolves a group argument to its numeric id. If `id` already parses as a
+/// number it is returned unchanged without touching the bridge; otherwise
+/// all groups are fetched and matched by name.
+fn resolve_group_id(bridge: &Bridge, id: String) -> Result<String> {
+    if id.parse::<u32>().is_ok() {
+        return Ok(id);
+    }
+
+    let candidates = bridge
+        .get_all_groups()?
+        .into_iter()
+        .map(|group| (group.id, group.name))
+        .collect();
+
+    resolve_id(id, candidates)
 }
 
 fn cmd_scan(bridge: Bridge) -> Result<()> {
@@ -57,16 +310,54 @@ fn cmd_scan(bridge: Bridge) -> Result<()> {
     Ok(())
 }
 
-fn cmd_list(bridge: Bridge) -> Result<()> {
-    let mut table = Table::new();
-    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+/// JSON representation of a light for `--json` output. huelib's `Light`
+/// only derives `Deserialize`, so this mirrors the fields we want to expose
+/// rather than serializing the bridge type directly.
+#[derive(Debug, Serialize)]
+struct LightJson {
+    id: String,
+    name: String,
+    reachable: bool,
+    on: Option<bool>,
+    brightness: Option<u8>,
+    hue: Option<u16>,
+    saturation: Option<u8>,
+    color_temperature: Option<u16>,
+    xy: Option<(f32, f32)>,
+}
 
-    table.set_titles(row!["id", "name", "reachable", "on"]);
+impl From<huelib::resource::Light> for LightJson {
+    fn from(light: huelib::resource::Light) -> Self {
+        Self {
+            id: light.id,
+            name: light.name,
+            reachable: light.state.reachable,
+            on: light.state.on,
+            brightness: light.state.brightness,
+            hue: light.state.hue,
+            saturation: light.state.saturation,
+            color_temperature: light.state.color_temperature,
+            xy: light.state.color_space_coordinates,
+        }
+    }
+}
 
+fn cmd_list(bridge: Bridge, json: bool) -> Result<()> {
     let mut lights = bridge.get_all_lights()?;
 
     lights.sort_by(|a, b| a.id.cmp(&b.id));
 
+    if json {
+        let lights: Vec<LightJson> = lights.into_iter().map(LightJson::from).collect();
+        println!("{}", serde_json::to_string_pretty(&lights)?);
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+    table.set_titles(row!["id", "name", "reachable", "on"]);
+
     for light in lights {
         table.add_row(row![
             light.id.to_string(),
@@ -93,7 +384,61 @@ fn cmd_list(bridge: Bridge) -> Result<()> {
     Ok(())
 }
 
+fn cmd_watch(bridge: Bridge, interval: u64) -> Result<()> {
+    let interval = interval.max(1);
+    let mut previous: HashMap<String, (bool, Option<bool>)> = HashMap::new();
+
+    loop {
+        let mut lights = match bridge.get_all_lights() {
+            Ok(lights) => lights,
+            Err(err) => {
+                eprintln!("error polling bridge: {}; retrying in {}s", err, interval);
+                std::thread::sleep(Duration::from_secs(interval));
+                continue;
+            }
+        };
+        lights.sort_by(|a, b| a.id.cmp(&b.id));
+
+        // Clear the terminal and move the cursor back to the top-left.
+        print!("\x1B[2J\x1B[H");
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        table.set_titles(row!["id", "name", "reachable", "on", "changed"]);
+
+        let mut current = HashMap::new();
+
+        for light in lights {
+            let state = (light.state.reachable, light.state.on);
+            let changed = previous.get(&light.id).map_or(false, |prev| *prev != state);
+
+            table.add_row(row![
+                light.id.to_string(),
+                light.name,
+                if light.state.reachable { "yes" } else { "no" },
+                light
+                    .state
+                    .on
+                    .map(|on| if on { "yes" } else { "no" })
+                    .unwrap_or("-"),
+                if changed { "*" } else { "" },
+            ]);
+
+            current.insert(light.id, state);
+        }
+
+        table.printstd();
+
+        previous = current;
+
+        std::thread::sleep(Duration::from_secs(interval));
+    }
+}
+
 fn cmd_blink(bridge: Bridge, id: String) -> Result<()> {
+    let id = resolve_light_id(&bridge, id)?;
+
     println!("Blinking light {}...", id);
 
     let mut on = true;
@@ -107,7 +452,211 @@ fn cmd_blink(bridge: Bridge, id: String) -> Result<()> {
     }
 }
 
+/// JSON representation of a group for `--json` output. See `LightJson` for
+/// why we don't serialize huelib's `Group` directly.
+#[derive(Debug, Serialize)]
+struct GroupJson {
+    id: String,
+    name: String,
+    kind: String,
+    lights: Vec<String>,
+    all_on: bool,
+    any_on: bool,
+}
+
+impl From<huelib::resource::Group> for GroupJson {
+    fn from(group: huelib::resource::Group) -> Self {
+        Self {
+            id: group.id,
+            name: group.name,
+            kind: group.kind.to_string(),
+            lights: group.lights,
+            all_on: group.state.all_on,
+            any_on: group.state.any_on,
+        }
+    }
+}
+
+fn cmd_group_list(bridge: Bridge, json: bool) -> Result<()> {
+    let mut groups = bridge.get_all_groups()?;
+
+    groups.sort_by(|a, b| a.id.cmp(&b.id));
+
+    if json {
+        let groups: Vec<GroupJson> = groups.into_iter().map(GroupJson::from).collect();
+        println!("{}", serde_json::to_string_pretty(&groups)?);
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+    table.set_titles(row!["id", "name", "type", "lights", "all on", "any on"]);
+
+    for group in groups {
+        table.add_row(row![
+            group.id.to_string(),
+            group.name,
+            group.kind.to_string(),
+            group.lights.len().to_string(),
+            if group.state.all_on { "yes" } else { "no" },
+            if group.state.any_on { "yes" } else { "no" },
+        ]);
+    }
+
+    table.printstd();
+
+    Ok(())
+}
+
+fn cmd_group_state(bridge: Bridge, id: String, on: bool) -> Result<()> {
+    let id = resolve_group_id(&bridge, id)?;
+
+    let modifier = GroupStateModifier::new().with_on(on);
+    bridge.set_group_state(id.clone(), &modifier)?;
+
+    println!(
+        "Set group {} to {}",
+        id,
+        if on { "on" } else { "off" }
+    );
+
+    Ok(())
+}
+
+fn cmd_group(bridge: Bridge, command: GroupCommand, json: bool) -> Result<()> {
+    match command {
+        GroupCommand::List => cmd_group_list(bridge, json),
+        GroupCommand::On { id } => cmd_group_state(bridge, id, true),
+        GroupCommand::Off { id } => cmd_group_state(bridge, id, false),
+        GroupCommand::State { id, on } => cmd_group_state(bridge, id, on),
+    }
+}
+
+/// JSON representation of a scene for `--json` output. See `LightJson` for
+/// why we don't serialize huelib's `Scene` directly.
+#[derive(Debug, Serialize)]
+struct SceneJson {
+    id: String,
+    name: String,
+    lights: Option<Vec<String>>,
+}
+
+impl From<Scene> for SceneJson {
+    fn from(scene: Scene) -> Self {
+        Self {
+            id: scene.id,
+            name: scene.name,
+            lights: scene.lights,
+        }
+    }
+}
+
+fn cmd_scene_list(bridge: Bridge, json: bool) -> Result<()> {
+    let mut scenes = bridge.get_all_scenes()?;
+
+    scenes.sort_by(|a, b| a.id.cmp(&b.id));
+
+    if json {
+        let scenes: Vec<SceneJson> = scenes.into_iter().map(SceneJson::from).collect();
+        println!("{}", serde_json::to_string_pretty(&scenes)?);
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+    table.set_titles(row!["id", "name", "lights"]);
+
+    for scene in scenes {
+        table.add_row(row![
+            scene.id.to_string(),
+            scene.name,
+            scene
+                .lights
+                .map(|lights| lights.join(", "))
+                .unwrap_or_else(|| "-".to_string()),
+        ]);
+    }
+
+    table.printstd();
+
+    Ok(())
+}
+
+fn cmd_scene_activate(bridge: Bridge, id: String) -> Result<()> {
+    let scenes = bridge.get_all_scenes()?;
+    let scene: Scene = scenes
+        .into_iter()
+        .find(|scene| scene.id == id)
+        .ok_or_else(|| anyhow!("no scene with id \"{}\" found", id))?;
+
+    // LightScenes (scenes built directly from a set of lights rather than a
+    // room/zone) have no `group`. The bridge only exposes scene activation
+    // through a group's action endpoint, so fall back to group "0", the
+    // implicit "all lights" group every bridge has, rather than refusing to
+    // activate the scene at all.
+    let group_id = scene.group.clone().unwrap_or_else(|| "0".to_string());
+
+    let modifier = GroupStateModifier::new().with_scene(scene.id.clone());
+    bridge.set_group_state(group_id, &modifier)?;
+
+    println!("Activated scene \"{}\" ({})", scene.name, id);
+
+    Ok(())
+}
+
+fn cmd_scene(bridge: Bridge, command: SceneCommand, json: bool) -> Result<()> {
+    match command {
+        SceneCommand::List => cmd_scene_list(bridge, json),
+        SceneCommand::Activate { id } => cmd_scene_activate(bridge, id),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cmd_set(
+    bridge: Bridge,
+    id: String,
+    brightness: Option<(ModifierType, i16)>,
+    hue: Option<(ModifierType, i32)>,
+    saturation: Option<(ModifierType, i16)>,
+    color_temp: Option<(ModifierType, i16)>,
+    xy: Option<(f32, f32)>,
+    transition: Option<u64>,
+) -> Result<()> {
+    let id = resolve_light_id(&bridge, id)?;
+
+    let mut modifier = StateModifier::new();
+
+    if let Some((modifier_type, value)) = brightness {
+        modifier = modifier.with_brightness(modifier_type, value);
+    }
+    if let Some((modifier_type, value)) = hue {
+        modifier = modifier.with_hue(modifier_type, value);
+    }
+    if let Some((modifier_type, value)) = saturation {
+        modifier = modifier.with_saturation(modifier_type, value);
+    }
+    if let Some((modifier_type, value)) = color_temp {
+        modifier = modifier.with_color_temperature(modifier_type, value);
+    }
+    if let Some((x, y)) = xy {
+        modifier = modifier.with_color_space_coordinates(x, y);
+    }
+    if let Some(transition_ms) = transition {
+        modifier = modifier.with_transition_time((transition_ms / 100) as u16);
+    }
+
+    bridge.set_light_state(id.clone(), &modifier)?;
+
+    println!("Updated light {}", id);
+
+    Ok(())
+}
+
 fn cmd_name(bridge: Bridge, id: String, name: String) -> Result<()> {
+    let id = resolve_light_id(&bridge, id)?;
+
     bridge.set_light_attribute(
         id.clone(),
         &AttributeModifier::new().with_name(name.clone()),
@@ -118,20 +667,22 @@ fn cmd_name(bridge: Bridge, id: String, name: String) -> Result<()> {
     Ok(())
 }
 
+fn discover_address() -> Result<IpAddr> {
+    let mut ip_addresses = huelib::bridge::discover_nupnp()?;
+    ip_addresses
+        .pop()
+        .ok_or_else(|| anyhow!("No bridge IP addresses found on the network"))
+}
+
 fn main() -> Result<()> {
     let args = Args::from_args();
 
-    let address = match args.ip_address {
-        Some(address) => address,
-        None => {
-            let mut ip_addresses = huelib::bridge::discover_nupnp()?;
-            ip_addresses
-                .pop()
-                .ok_or_else(|| anyhow!("No bridge IP addresses found on the network"))?
-        }
-    };
-
     if let Subcommand::Register = args.subcommand {
+        let address = match args.ip_address {
+            Some(address) => address,
+            None => discover_address()?,
+        };
+
         let username = huelib::bridge::register_user(address, "hue-rs-cli")?;
         println!("Username: {}", username);
 
@@ -142,13 +693,34 @@ fn main() -> Result<()> {
     let config_file = dirs
         .find_config_file("config.toml")
         .ok_or_else(|| anyhow!("no hue config file found in .config/hue"))?;
-    let config: Config = toml::from_str(
+    let config: Config = toml::from_str::<ConfigFile>(
         &std::fs::read_to_string(config_file.clone())
             .with_context(|| anyhow!("failed to read config file at {:?}", config_file))?,
     )
-    .with_context(|| anyhow!("failed to parse config file at {:?}", config_file))?;
+    .with_context(|| {
+        anyhow!(
+            "failed to parse config file at {:?}; expected either a [profiles.<name>] \
+             section with a \"default\" key, or a legacy \"username\" key",
+            config_file
+        )
+    })?
+    .into_config();
+
+    let profile_name = args.profile.as_ref().unwrap_or(&config.default);
+    let profile = config.profiles.get(profile_name).ok_or_else(|| {
+        anyhow!(
+            "no profile named \"{}\" found in {:?}",
+            profile_name,
+            config_file
+        )
+    })?;
+
+    let address = match args.ip_address.or(profile.ip_address) {
+        Some(address) => address,
+        None => discover_address()?,
+    };
 
-    let bridge = Bridge::new(address, &config.username);
+    let bridge = Bridge::new(address, &profile.username);
 
     match args.subcommand {
         Subcommand::Register => {
@@ -157,8 +729,20 @@ fn main() -> Result<()> {
             ));
         }
         Subcommand::Scan => cmd_scan(bridge),
-        Subcommand::List => cmd_list(bridge),
+        Subcommand::List => cmd_list(bridge, args.json),
         Subcommand::Blink { id } => cmd_blink(bridge, id),
         Subcommand::Name { id, name } => cmd_name(bridge, id, name),
+        Subcommand::Set {
+            id,
+            brightness,
+            hue,
+            saturation,
+            color_temp,
+            xy,
+            transition,
+        } => cmd_set(bridge, id, brightness, hue, saturation, color_temp, xy, transition),
+        Subcommand::Group(command) => cmd_group(bridge, command, args.json),
+        Subcommand::Scene(command) => cmd_scene(bridge, command, args.json),
+        Subcommand::Watch { interval } => cmd_watch(bridge, interval),
     }
 }